@@ -1,4 +1,5 @@
 use crate::optimizer::*;
+use crate::processor::ResizeProcessor;
 
 use leptos::*;
 use leptos_meta::Link;
@@ -13,10 +14,15 @@ pub fn Image<'a>(
     /// Image source. Should be path relative to root.
     #[prop(into)]
     src: String,
-    /// Resize image height.
-    height: u32,
-    /// Resize image width.
-    width: u32,
+    /// Resize image height. Leave unset to derive it from `width` and the source's
+    /// intrinsic aspect ratio, read via [`crate::ImageOptimizer`].
+    #[prop(optional)]
+    height: Option<u32>,
+    /// Resize image width. Leave unset to derive it from `height` and the source's
+    /// intrinsic aspect ratio. If both are left unset, the source's intrinsic
+    /// dimensions are used as-is.
+    #[prop(optional)]
+    width: Option<u32>,
     /// Image quality. 0-100.
     #[prop(default = 75_u8)]
     quality: u8,
@@ -26,6 +32,10 @@ pub fn Image<'a>(
     /// Resize type for the conversion : Fit, Cover, Thumbnail
     #[prop(default = "fit")]
     resize_type: &'a str,
+    /// Output format: Auto, Webp, Avif, Jpeg, Png. `Auto` negotiates the best format
+    /// the requesting browser supports via the `Accept` header.
+    #[prop(default = "auto")]
+    format: &'a str,
     /// Will add blur image to head if true.
     #[prop(default = false)]
     blur: bool,
@@ -51,7 +61,7 @@ pub fn Image<'a>(
     let blur_image = {
         CachedImage {
             src: src.clone(),
-            option: CachedImageOption::Blur(Blur {
+            option: CachedImageOption::BlurPlaceholder(Blur {
                 width: 20,
                 height: 20,
                 svg_width: 100,
@@ -61,24 +71,20 @@ pub fn Image<'a>(
         }
     };
 
-    let opt_image = {
-        CachedImage {
-            src: src.clone(),
-            option: CachedImageOption::Resize(Resize {
-                quality,
-                filter: filter.parse().unwrap_or_default(),
-                width,
-                height,
-                resize_type: resize_type.parse().unwrap_or_default(),
-            }),
-        }
-    };
-
     // Retrieve value from Cache if it exists. Doing this per-image to allow image introspection.
     let resource = crate::use_image_cache_resource();
+    // Only hits the network when `width` or `height` is left unset; otherwise resolves
+    // immediately to the props as given.
+    let dimensions = create_resource(
+        {
+            let src = src.clone();
+            move || (src.clone(), width, height)
+        },
+        |(src, width, height)| resolve_dimensions(src, width, height),
+    );
 
     let blur_image = store_value(blur_image);
-    let opt_image = store_value(opt_image);
+    let src = store_value(src);
     let alt = store_value(alt);
     let class = store_value(class.map(|c| c.into_attribute_boxed()));
 
@@ -87,10 +93,33 @@ pub fn Image<'a>(
             {move || {
                 resource
                     .get()
-                    .map(|config| {
+                    .zip(dimensions.get())
+                    .map(|(config, (width, height))| {
                         let images = config.cache;
                         let handler_path = config.api_handler_path;
-                        let opt_image = opt_image.get_value().get_url_encoded(&handler_path);
+                        let opt_image = CachedImage {
+                            src: src.get_value(),
+                            option: CachedImageOption::Processed(ProcessedImage::new(
+                                vec![Box::new(ResizeProcessor {
+                                    width,
+                                    height,
+                                    filter: filter.parse().unwrap_or_default(),
+                                    resize_type: resize_type.parse().unwrap_or_default(),
+                                })],
+                                quality,
+                                format.parse().unwrap_or_default(),
+                            )),
+                        };
+                        // Registers this render's variants so a later `generate_all` build
+                        // pass can pre-render them for static hosting. No-op on the client,
+                        // where no `ImageOptimizer` is in context.
+                        if let Some(optimizer) = use_context::<ImageOptimizer>() {
+                            optimizer.register(opt_image.clone());
+                            if blur {
+                                optimizer.register(blur_image.get_value());
+                            }
+                        }
+                        let opt_image = opt_image.get_url_encoded(&handler_path);
                         if blur {
                             let placeholder_svg = images
                                 .iter()
@@ -131,6 +160,38 @@ pub fn Image<'a>(
     }
 }
 
+/// Resolves the target `(width, height)` for an [`Image`]: explicit props pass through
+/// unchanged; a missing one is derived from the source's intrinsic aspect ratio via
+/// [`crate::get_source_dimensions`]; if both are missing, the intrinsic dimensions are
+/// used as-is.
+async fn resolve_dimensions(src: String, width: Option<u32>, height: Option<u32>) -> (u32, u32) {
+    match (width, height) {
+        (Some(width), Some(height)) => (width, height),
+        (width, height) => match crate::get_source_dimensions(src).await {
+            Ok(dimensions) => {
+                let width = width.unwrap_or_else(|| {
+                    height
+                        .map(|height| scale(height, dimensions.width, dimensions.height))
+                        .unwrap_or(dimensions.width)
+                });
+                let height =
+                    height.unwrap_or_else(|| scale(width, dimensions.height, dimensions.width));
+                (width, height)
+            }
+            Err(e) => {
+                logging::error!("Failed to read source image dimensions: {:?}", e);
+                (width.unwrap_or_default(), height.unwrap_or_default())
+            }
+        },
+    }
+}
+
+/// Scales `value` by the `numerator`/`denominator` aspect ratio, e.g. deriving a target
+/// height from a target width and the source's `height`/`width` ratio.
+fn scale(value: u32, numerator: u32, denominator: u32) -> u32 {
+    (value as u64 * numerator as u64 / denominator.max(1) as u64) as u32
+}
+
 enum SvgImage {
     InMemory(String),
     Request(String),
@@ -183,65 +244,3 @@ fn CacheImage(
         />
     }
 }
-
-
-
-
-pub struct Ruleset{
-    pub width: u32,
-    pub height: u32,
-    pub quality: u8,
-    pub filter: String,
-    pub resize_type: String,
-}
-/// Picture component for rendering optimized static images.
-/// Images MUST be static. Will not work with dynamic images.
-/// Will resize an image based on rules and dimensions.
-#[component]
-pub fn Picture(
-    /// Image source. Should be path relative to root.
-    #[prop(into)] ///
-    src: String, ///
-    /// A rule that based on screen width and height will return a Resize struct.
-    ruleset: fn(usize, usize) -> Ruleset,
-    /// Will add blur image to head if true.
-    #[prop(default = false)]
-    blur: bool,
-    /// Will add preload to the head if true.
-    #[prop(default = false)]
-    priority: bool,
-    /// Will add lazy loading to the head if true.
-    #[prop(default = true)]
-    lazy: bool,
-    /// Image alt text.
-    #[prop(into, optional)]
-    alt: String,
-    /// Style class for image
-    #[prop(into, optional)]
-    class: Option<AttributeValue>,
-) -> impl IntoView {
-    let screen = leptos::window();
-    let screen_width = screen.inner_width().unwrap_or_default().as_f64().unwrap_or_default() as usize;
-    let screen_height = screen.inner_height().unwrap_or_default().as_f64().unwrap_or_default() as usize;
-
-    let rules = ruleset(screen_width, screen_height);
-
-
-
-    view! {
-
-        <Image
-            src=src
-            alt=alt
-            class=class
-            priority=priority
-            blur=blur
-            lazy=lazy
-            width=rules.width
-            height=rules.height
-            quality=rules.quality
-            resize_type=&rules.resize_type
-            filter=&rules.filter
-        />
-    }
-}
@@ -0,0 +1,95 @@
+#![forbid(unsafe_code)]
+
+//! Image optimization for the Leptos web framework.
+//!
+//! Provides [`Image`] and [`Picture`] components that lazily resize, blur-placeholder,
+//! and cache static images, plus an axum route for serving the cached variants.
+
+mod image;
+mod optimizer;
+mod picture;
+mod processor;
+mod routes;
+
+pub use crate::image::Image;
+pub use optimizer::*;
+pub use picture::Picture;
+pub use processor::{
+    BlurProcessor, CropProcessor, Flip, GrayscaleProcessor, Processor, ResizeProcessor,
+    RotateProcessor, WatermarkPosition, WatermarkProcessor,
+};
+pub use routes::*;
+
+use leptos::*;
+
+/// Provides the [`CacheImageConfig`] resource used by [`Image`] and [`Picture`] to
+/// look up cached blur placeholders and the API handler path.
+///
+/// Call this once near the root of your application.
+pub fn provide_image_context() {
+    let resource = create_resource(
+        || (),
+        |_| async move { get_image_cache_entries().await.unwrap_or_default() },
+    );
+    provide_context(resource);
+}
+
+/// Retrieves the [`CacheImageConfig`] resource provided by [`provide_image_context`].
+pub fn use_image_cache_resource() -> Resource<(), CacheImageConfig> {
+    use_context::<Resource<(), CacheImageConfig>>()
+        .expect("Missing ImageCacheContext. Did you call provide_image_context()?")
+}
+
+#[server(GetImageCacheEntries, "/api")]
+async fn get_image_cache_entries() -> Result<CacheImageConfig, ServerFnError> {
+    let optimizer = use_context::<ImageOptimizer>()
+        .ok_or_else(|| ServerFnError::ServerError("Missing ImageOptimizer context".to_string()))?;
+
+    Ok(CacheImageConfig {
+        cache: optimizer
+            .cache
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect(),
+        api_handler_path: optimizer.api_handler_path.clone(),
+    })
+}
+
+/// Reads `src`'s intrinsic dimensions, so [`Image`] can derive a `width` or `height`
+/// prop that was left unset.
+#[server(GetSourceDimensions, "/api")]
+pub(crate) async fn get_source_dimensions(src: String) -> Result<ImageDimensions, ServerFnError> {
+    let optimizer = use_context::<ImageOptimizer>()
+        .ok_or_else(|| ServerFnError::ServerError("Missing ImageOptimizer context".to_string()))?;
+
+    optimizer
+        .read_image_dimensions(&src)
+        .await
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))
+}
+
+/// Opt-in build pass for fully static hosting: renders `app_fn` once so every
+/// `<Image>`/`<Picture>` it contains registers its variants with `optimizer` (see
+/// [`ImageOptimizer::register`]), then eagerly creates all of them via
+/// [`ImageOptimizer::generate_all`] and returns how many were newly written.
+///
+/// Call this from your own build script or CLI entrypoint, not from the running
+/// server. It's safe to run alongside a live [`ImageCacheRoute`] handler afterward:
+/// that handler falls back to creating on-demand any variant added since the last
+/// pass, the same incremental-static-regeneration tradeoff `create_image` already
+/// makes a no-op for anything already on disk.
+pub async fn generate_image_cache<F, IV>(
+    app_fn: F,
+    optimizer: &ImageOptimizer,
+) -> Result<usize, CreateImageError>
+where
+    F: FnOnce() -> IV + 'static,
+    IV: IntoView,
+{
+    let provide_context = optimizer.provide_context();
+    leptos::ssr::render_to_string(move || {
+        provide_context();
+        app_fn()
+    });
+    optimizer.generate_all().await
+}
@@ -0,0 +1,686 @@
+use crate::processor::{canonical_order, parse_processor, Processor};
+use base64::{engine::general_purpose, Engine as _};
+use dashmap::{DashMap, DashSet};
+use image::{imageops::FilterType as ImageFilterType, DynamicImage, GenericImageView};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A fully resolved image + transform pipeline, uniquely identifying one cached
+/// variant on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CachedImage {
+    /// Path to the source image, relative to `site_root`.
+    pub src: String,
+    pub option: CachedImageOption,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CachedImageOption {
+    Processed(ProcessedImage),
+    BlurPlaceholder(Blur),
+}
+
+/// An ordered pipeline of [`Processor`]s applied to the source image, then encoded
+/// at `quality` into `format`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcessedImage {
+    pub processors: Vec<Box<dyn Processor>>,
+    pub quality: u8,
+    /// Output encoding. `Auto` is resolved against the request's `Accept` header.
+    pub format: OutputFormat,
+}
+
+impl ProcessedImage {
+    /// Builds a pipeline, normalizing `processors` into [`canonical_order`] up front.
+    ///
+    /// This keeps construction order irrelevant everywhere downstream: the derived
+    /// `Eq`/`Hash` (used by [`ImageOptimizer::registered`] and the `cache` map),
+    /// `get_url_encoded`'s cache key, and the actual pixel transform in
+    /// `create_image_blocking` all agree on one order, so two pipelines built with the
+    /// same processors in different insertion order collapse to the same entry instead
+    /// of silently racing for the same file path.
+    pub fn new(mut processors: Vec<Box<dyn Processor>>, quality: u8, format: OutputFormat) -> Self {
+        processors.sort_by_key(|p| canonical_order(p.name()));
+        Self {
+            processors,
+            quality,
+            format,
+        }
+    }
+}
+
+/// Parameters for generating a tiny blurred SVG placeholder, embedded inline as a
+/// data URI while the real image loads. Distinct from [`crate::BlurProcessor`], which
+/// blurs the full-size output image instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Blur {
+    pub width: u32,
+    pub height: u32,
+    pub svg_width: u32,
+    pub svg_height: u32,
+    pub sigma: u8,
+}
+
+/// Output image format. `Auto` defers the choice to [`OutputFormat::resolve`],
+/// which inspects the request's `Accept` header so the server can transparently
+/// serve WebP/AVIF to browsers that support it and fall back to Jpeg otherwise.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    #[default]
+    Auto,
+    Webp,
+    Avif,
+    Jpeg,
+    Png,
+}
+
+impl OutputFormat {
+    /// Resolves `Auto` into a concrete format using the request's `Accept` header.
+    /// Formats other than `Auto` are returned unchanged.
+    pub fn resolve(self, accept: Option<&str>) -> OutputFormat {
+        match self {
+            OutputFormat::Auto => {
+                let accept = accept.unwrap_or_default();
+                if accept.contains("image/avif") {
+                    OutputFormat::Avif
+                } else if accept.contains("image/webp") {
+                    OutputFormat::Webp
+                } else {
+                    OutputFormat::Jpeg
+                }
+            }
+            other => other,
+        }
+    }
+
+    /// Every concrete format [`Self::resolve`] can produce from `Auto`, in no
+    /// particular order. Used to pre-generate one variant per possible negotiation
+    /// outcome when the real `Accept` header isn't available yet (e.g. at build time).
+    pub fn auto_variants() -> [OutputFormat; 3] {
+        [OutputFormat::Avif, OutputFormat::Webp, OutputFormat::Jpeg]
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Auto | OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Png => "png",
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "webp" => Ok(Self::Webp),
+            "avif" => Ok(Self::Avif),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "png" => Ok(Self::Png),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            Self::Auto => "auto",
+            Self::Webp => "webp",
+            Self::Avif => "avif",
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResizeType {
+    #[default]
+    Fit,
+    Cover,
+    Thumbnail,
+}
+
+impl FromStr for ResizeType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fit" => Ok(Self::Fit),
+            "cover" => Ok(Self::Cover),
+            "thumbnail" => Ok(Self::Thumbnail),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for ResizeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            Self::Fit => "fit",
+            Self::Cover => "cover",
+            Self::Thumbnail => "thumbnail",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterType {
+    Nearest,
+    Triangle,
+    #[default]
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+
+impl FromStr for FilterType {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nearest" => Ok(Self::Nearest),
+            "triangle" => Ok(Self::Triangle),
+            "catmullrom" => Ok(Self::CatmullRom),
+            "gaussian" => Ok(Self::Gaussian),
+            "lanczos3" => Ok(Self::Lanczos3),
+            _ => Err(()),
+        }
+    }
+}
+
+impl std::fmt::Display for FilterType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            Self::Nearest => "nearest",
+            Self::Triangle => "triangle",
+            Self::CatmullRom => "catmullrom",
+            Self::Gaussian => "gaussian",
+            Self::Lanczos3 => "lanczos3",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl From<FilterType> for ImageFilterType {
+    fn from(filter: FilterType) -> Self {
+        match filter {
+            FilterType::Nearest => ImageFilterType::Nearest,
+            FilterType::Triangle => ImageFilterType::Triangle,
+            FilterType::CatmullRom => ImageFilterType::CatmullRom,
+            FilterType::Gaussian => ImageFilterType::Gaussian,
+            FilterType::Lanczos3 => ImageFilterType::Lanczos3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum CreateImageError {
+    #[error("Image error: {0}")]
+    ImageError(String),
+    #[error("Io error: {0}")]
+    IoError(String),
+    #[error("Invalid cached image url")]
+    InvalidUrl,
+}
+
+impl From<image::ImageError> for CreateImageError {
+    fn from(e: image::ImageError) -> Self {
+        Self::ImageError(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for CreateImageError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IoError(e.to_string())
+    }
+}
+
+impl CachedImage {
+    /// Encodes this cached image into a URL for the image cache handler route.
+    ///
+    /// Each processor contributes its own `name=value` query segment (via
+    /// [`Processor::cache_key_segment`]), so the URL doubles as the cache key.
+    pub fn get_url_encoded(&self, handler_path: &str) -> String {
+        let src_encoded = general_purpose::URL_SAFE_NO_PAD.encode(self.src.as_bytes());
+        let mut pairs = vec![format!("src={src_encoded}")];
+
+        match &self.option {
+            CachedImageOption::Processed(processed) => {
+                // `processed.processors` is already in canonical order (see
+                // `ProcessedImage::new`), so no re-sort is needed here.
+                for processor in &processed.processors {
+                    pairs.push(format!(
+                        "{}={}",
+                        processor.name(),
+                        processor.cache_key_segment()
+                    ));
+                }
+                pairs.push(format!("q={}", processed.quality));
+                pairs.push(format!("fmt={}", processed.format));
+            }
+            CachedImageOption::BlurPlaceholder(blur) => {
+                pairs.push(format!(
+                    "placeholder={}x{}x{}x{}x{}",
+                    blur.width, blur.height, blur.svg_width, blur.svg_height, blur.sigma
+                ));
+            }
+        }
+
+        format!("{handler_path}?{}", pairs.join("&"))
+    }
+
+    /// Decodes a [`CachedImage`] from a URL previously produced by [`Self::get_url_encoded`].
+    pub fn from_url_encoded(url: &str) -> Result<Self, CreateImageError> {
+        let query = url.split('?').nth(1).ok_or(CreateImageError::InvalidUrl)?;
+
+        let mut src = None;
+        let mut processors = Vec::new();
+        let mut quality = None;
+        let mut format = OutputFormat::default();
+        let mut placeholder = None;
+
+        for pair in query.split('&') {
+            let (key, value) = pair.split_once('=').ok_or(CreateImageError::InvalidUrl)?;
+            match key {
+                "src" => {
+                    let bytes = general_purpose::URL_SAFE_NO_PAD
+                        .decode(value)
+                        .map_err(|_| CreateImageError::InvalidUrl)?;
+                    src = Some(String::from_utf8(bytes).map_err(|_| CreateImageError::InvalidUrl)?);
+                }
+                "q" => quality = Some(value.parse().map_err(|_| CreateImageError::InvalidUrl)?),
+                "fmt" => format = value.parse().unwrap_or_default(),
+                "placeholder" => {
+                    let mut parts = value.split('x');
+                    let mut next = || parts.next().and_then(|v| v.parse().ok());
+                    placeholder = Some(Blur {
+                        width: next().ok_or(CreateImageError::InvalidUrl)?,
+                        height: next().ok_or(CreateImageError::InvalidUrl)?,
+                        svg_width: next().ok_or(CreateImageError::InvalidUrl)?,
+                        svg_height: next().ok_or(CreateImageError::InvalidUrl)?,
+                        sigma: next().ok_or(CreateImageError::InvalidUrl)?,
+                    });
+                }
+                key => {
+                    if let Some(processor) = parse_processor(key, value) {
+                        processors.push(processor);
+                    }
+                }
+            }
+        }
+
+        let src = src.ok_or(CreateImageError::InvalidUrl)?;
+
+        let option = if let Some(blur) = placeholder {
+            CachedImageOption::BlurPlaceholder(blur)
+        } else {
+            CachedImageOption::Processed(ProcessedImage::new(
+                processors,
+                quality.unwrap_or(75),
+                format,
+            ))
+        };
+
+        Ok(CachedImage { src, option })
+    }
+
+    /// Path of the cached variant, relative to `site_root`.
+    ///
+    /// Content-addressed: the path is derived entirely from the hash of `self`, so any
+    /// change to the source path, processor pipeline, or format produces a new,
+    /// distinct path. Combined with the immutable `Cache-Control` the handler emits,
+    /// this lets old URLs stay cacheable forever.
+    pub fn get_file_path(&self) -> String {
+        let ext = match &self.option {
+            CachedImageOption::Processed(processed) => processed.format.resolve(None).extension(),
+            CachedImageOption::BlurPlaceholder(_) => "svg",
+        };
+        format!("cache/image/{}.{ext}", self.content_hash())
+    }
+
+    /// Hex-encoded SHA-256 digest of this cached image's source path and pipeline.
+    pub fn content_hash(&self) -> String {
+        let canonical = self.get_url_encoded("");
+        let digest = Sha256::digest(canonical.as_bytes());
+        digest.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+}
+
+/// Intrinsic pixel dimensions of a source image, read from just its header without
+/// decoding the full image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageDimensions {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Snapshot of the [`ImageOptimizer`]'s blur-placeholder cache and API handler path,
+/// provided to [`crate::Image`] and [`crate::Picture`] via [`crate::use_image_cache_resource`].
+#[derive(Debug, Clone, Default)]
+pub struct CacheImageConfig {
+    pub cache: Vec<(CachedImage, String)>,
+    pub api_handler_path: String,
+}
+
+/// Lazily transforms, blurs, and caches static images on disk, and serves them through
+/// [`crate::ImageCacheRoute`].
+#[derive(Clone)]
+pub struct ImageOptimizer {
+    pub(crate) cache: Arc<DashMap<CachedImage, String>>,
+    /// Every variant an `<Image>`/`<Picture>` has rendered and registered via
+    /// [`Self::register`], across every responsive width and format. Walked by
+    /// [`Self::generate_all`] to pre-render the full set for static hosting.
+    registered: Arc<DashSet<CachedImage>>,
+    pub site_root: String,
+    pub api_handler_path: String,
+    semaphore: Arc<tokio::sync::Semaphore>,
+}
+
+impl ImageOptimizer {
+    /// Creates a new optimizer rooted at `site_root`, running at most `concurrency`
+    /// image transforms at a time.
+    pub fn new(site_root: impl Into<String>, concurrency: usize) -> Self {
+        Self {
+            cache: Arc::new(DashMap::new()),
+            registered: Arc::new(DashSet::new()),
+            site_root: site_root.into(),
+            api_handler_path: "/api/image".to_string(),
+            semaphore: Arc::new(tokio::sync::Semaphore::new(concurrency.max(1))),
+        }
+    }
+
+    /// Records `image` as part of the app's full variant set. Called by `<Image>` and
+    /// `<Picture>` for every variant they render, so a later [`Self::generate_all`]
+    /// build pass can find it even if this exact request is never served again.
+    ///
+    /// `image` is registered the same way [`crate::routes::check_cache_image`] would
+    /// resolve it for a real request: a concrete `format` is registered as-is, but
+    /// `Auto` is expanded into one registration per [`OutputFormat::auto_variants`],
+    /// since which one a real browser negotiates isn't known yet here. Without this,
+    /// `generate_all` would hash and write a file keyed on the literal `fmt=auto`, which
+    /// no live request — `Auto` is always resolved to a concrete format before hashing —
+    /// would ever ask for.
+    pub fn register(&self, image: CachedImage) {
+        if let CachedImageOption::Processed(processed) = &image.option {
+            if processed.format == OutputFormat::Auto {
+                for format in OutputFormat::auto_variants() {
+                    let mut variant = image.clone();
+                    if let CachedImageOption::Processed(processed) = &mut variant.option {
+                        processed.format = format;
+                    }
+                    self.registered.insert(variant);
+                }
+                return;
+            }
+        }
+        self.registered.insert(image);
+    }
+
+    /// Eagerly runs [`Self::create_image`] for every variant registered so far via
+    /// [`Self::register`] — every `<Image>`/`<Picture>` the app has rendered, including
+    /// blur placeholders and every responsive width — so the site can be served
+    /// entirely from `site_root` with no running [`crate::ImageCacheRoute`] handler.
+    ///
+    /// Returns the number of variants newly written to disk. Safe to call repeatedly
+    /// or alongside a running handler: [`Self::create_image`] is a no-op for a variant
+    /// that already exists, and the handler falls back to creating on-demand whatever
+    /// this pass hasn't covered yet.
+    pub async fn generate_all(&self) -> Result<usize, CreateImageError> {
+        let mut created = 0;
+        for image in self.registered.iter() {
+            if self.create_image(&image).await? {
+                created += 1;
+            }
+        }
+        Ok(created)
+    }
+
+    /// Provides this optimizer via Leptos context, so server functions and routes can find it.
+    pub fn provide_context(&self) -> impl Fn() + Clone {
+        let optimizer = self.clone();
+        move || leptos::provide_context(optimizer.clone())
+    }
+
+    pub fn get_file_path_from_root(&self, image: &CachedImage) -> PathBuf {
+        PathBuf::from(&self.site_root).join(image.get_file_path())
+    }
+
+    /// Reads `src`'s intrinsic pixel dimensions from its header, without decoding the
+    /// full image. Used to derive a missing `width`/`height` prop on [`crate::Image`].
+    pub async fn read_image_dimensions(
+        &self,
+        src: &str,
+    ) -> Result<ImageDimensions, CreateImageError> {
+        let src_path = PathBuf::from(&self.site_root).join(src.trim_start_matches('/'));
+
+        tokio::task::spawn_blocking(move || {
+            let (width, height) = image::io::Reader::open(&src_path)?
+                .with_guessed_format()?
+                .into_dimensions()?;
+            Ok(ImageDimensions { width, height })
+        })
+        .await
+        .map_err(|e| CreateImageError::IoError(e.to_string()))?
+    }
+
+    /// Creates the cached variant for `image` on disk if it doesn't already exist.
+    /// Returns `Ok(true)` if the file was newly created.
+    pub async fn create_image(&self, image: &CachedImage) -> Result<bool, CreateImageError> {
+        let dest_path = self.get_file_path_from_root(image);
+        if dest_path.exists() {
+            return Ok(false);
+        }
+
+        let _permit = self
+            .semaphore
+            .acquire()
+            .await
+            .map_err(|e| CreateImageError::IoError(e.to_string()))?;
+
+        // Re-check under the permit in case a concurrent request already created it.
+        if dest_path.exists() {
+            return Ok(false);
+        }
+
+        let src_path = PathBuf::from(&self.site_root).join(image.src.trim_start_matches('/'));
+        let image = image.clone();
+
+        tokio::task::spawn_blocking(move || create_image_blocking(&src_path, &dest_path, &image))
+            .await
+            .map_err(|e| CreateImageError::IoError(e.to_string()))??;
+
+        Ok(true)
+    }
+}
+
+fn create_image_blocking(
+    src_path: &Path,
+    dest_path: &Path,
+    image: &CachedImage,
+) -> Result<(), CreateImageError> {
+    if let Some(parent) = dest_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let source = image::open(src_path)?;
+
+    match &image.option {
+        CachedImageOption::Processed(processed) => {
+            let processed_image = processed
+                .processors
+                .iter()
+                .fold(source, |img, processor| processor.process(img));
+            save_image(&processed_image, dest_path, processed)
+        }
+        CachedImageOption::BlurPlaceholder(blur) => {
+            create_blur_placeholder(&source, blur, dest_path)
+        }
+    }
+}
+
+fn save_image(
+    img: &DynamicImage,
+    dest_path: &Path,
+    processed: &ProcessedImage,
+) -> Result<(), CreateImageError> {
+    // The format has already been resolved (from `Auto`) by the caller that built
+    // `processed`, e.g. `check_cache_image`, before the file path and cache key were
+    // derived.
+    match processed.format.resolve(None) {
+        OutputFormat::Jpeg | OutputFormat::Auto => {
+            let mut out = std::fs::File::create(dest_path)?;
+            let encoder =
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, processed.quality);
+            img.write_with_encoder(encoder)?;
+        }
+        OutputFormat::Png => {
+            img.save_with_format(dest_path, image::ImageFormat::Png)?;
+        }
+        OutputFormat::Webp => {
+            // `image`'s own WebP encoder is lossless-only, so `quality` has nothing to
+            // control there; the `webp` crate wraps libwebp's lossy encoder instead.
+            let encoder = webp::Encoder::from_image(img)
+                .map_err(|e| CreateImageError::ImageError(e.to_string()))?;
+            let data = encoder.encode(processed.quality as f32);
+            std::fs::write(dest_path, &*data)?;
+        }
+        OutputFormat::Avif => {
+            let mut out = std::fs::File::create(dest_path)?;
+            let encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                &mut out,
+                6,
+                processed.quality,
+            );
+            img.write_with_encoder(encoder)?;
+        }
+    }
+    Ok(())
+}
+
+fn create_blur_placeholder(
+    source: &DynamicImage,
+    blur: &Blur,
+    dest_path: &Path,
+) -> Result<(), CreateImageError> {
+    let (width, height) = source.dimensions();
+    let thumbnail = if width > blur.width || height > blur.height {
+        source.thumbnail(blur.width, blur.height)
+    } else {
+        source.clone()
+    };
+
+    let mut buffer = Vec::new();
+    let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, 50);
+    thumbnail.write_with_encoder(encoder)?;
+    let encoded = general_purpose::STANDARD.encode(&buffer);
+
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {sw} {sh}"><filter id="b"><feGaussianBlur stdDeviation="{sigma}"/></filter><image filter="url(#b)" preserveAspectRatio="none" x="0" y="0" height="100%" width="100%" href="data:image/jpeg;base64,{encoded}"/></svg>"#,
+        sw = blur.svg_width,
+        sh = blur.svg_height,
+        sigma = blur.sigma,
+    );
+
+    std::fs::write(dest_path, svg)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::{CropProcessor, GrayscaleProcessor, ResizeProcessor};
+
+    fn multi_processor_image() -> CachedImage {
+        // Built in a deliberately non-canonical order (grayscale, then resize, then
+        // crop) to exercise `ProcessedImage::new`'s normalization.
+        CachedImage {
+            src: "images/cat.jpg".to_string(),
+            option: CachedImageOption::Processed(ProcessedImage::new(
+                vec![
+                    Box::new(GrayscaleProcessor),
+                    Box::new(ResizeProcessor {
+                        width: 800,
+                        height: 600,
+                        filter: FilterType::CatmullRom,
+                        resize_type: ResizeType::Fit,
+                    }),
+                    Box::new(CropProcessor {
+                        width: 400,
+                        height: 300,
+                    }),
+                ],
+                80,
+                OutputFormat::Webp,
+            )),
+        }
+    }
+
+    #[test]
+    fn url_round_trips_through_encode_decode() {
+        let image = multi_processor_image();
+        let encoded = image.get_url_encoded("/api/image");
+        let decoded = CachedImage::from_url_encoded(&encoded).unwrap();
+        assert_eq!(image, decoded);
+    }
+
+    #[test]
+    fn processors_are_canonicalized_regardless_of_construction_order() {
+        let CachedImageOption::Processed(processed) = multi_processor_image().option else {
+            unreachable!()
+        };
+        let names: Vec<_> = processed.processors.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["crop", "resize", "grayscale"]);
+    }
+
+    #[test]
+    fn content_hash_is_stable_and_order_independent() {
+        let a = multi_processor_image();
+
+        // Same processors, different insertion order: should canonicalize to the same
+        // hash, so two callers building the pipeline in different order land on the
+        // same file path instead of racing each other.
+        let CachedImageOption::Processed(processed) = &a.option else {
+            unreachable!()
+        };
+        let mut reordered = processed.processors.clone();
+        reordered.reverse();
+        let b = CachedImage {
+            src: a.src.clone(),
+            option: CachedImageOption::Processed(ProcessedImage::new(
+                reordered,
+                processed.quality,
+                processed.format,
+            )),
+        };
+
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_eq!(a.content_hash(), a.content_hash());
+    }
+
+    #[test]
+    fn resolve_auto_picks_best_supported_format() {
+        assert_eq!(
+            OutputFormat::Auto.resolve(Some("image/avif,image/webp,*/*")),
+            OutputFormat::Avif
+        );
+        assert_eq!(
+            OutputFormat::Auto.resolve(Some("image/webp,*/*")),
+            OutputFormat::Webp
+        );
+        assert_eq!(OutputFormat::Auto.resolve(Some("*/*")), OutputFormat::Jpeg);
+        assert_eq!(OutputFormat::Auto.resolve(None), OutputFormat::Jpeg);
+        assert_eq!(
+            OutputFormat::Jpeg.resolve(Some("image/avif")),
+            OutputFormat::Jpeg
+        );
+    }
+}
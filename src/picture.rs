@@ -1,18 +1,47 @@
-use crate::optimizer::Resize;
-use crate::Image;
-use leptos::{component, view, AttributeValue, IntoView};
+use crate::optimizer::{Blur, CachedImage, CachedImageOption, ProcessedImage};
+use crate::processor::ResizeProcessor;
+use leptos::{
+    component, store_value, use_context, view, AttributeValue, IntoAttribute, IntoView, SignalGet,
+    SignalWith, Suspense,
+};
+use leptos_meta::Link;
 
-/// Picture component for rendering optimized static images.
+/// Picture component for rendering responsive, server-rendered static images.
 /// Images MUST be static. Will not work with dynamic images.
-/// Will resize an image based on rules and dimensions.
+///
+/// Emits one optimized variant per entry in `widths` as a `srcset`, so the browser
+/// (not `leptos::window()`) picks the right image with no hydration-time correction
+/// and no SSR layout shift.
 #[component]
 pub fn Picture(
     /// Image source. Should be path relative to root.
-    #[prop(into)]///
-    src: String,///
-    /// A rule that based on screen width and height will return a Resize struct.
-    #[prop(into)] ruleset:
-    fn(usize, usize) -> Resize,
+    #[prop(into)]
+    src: String,
+    /// Target widths to generate responsive variants for, e.g. `vec![640, 960, 1280]`.
+    /// Each becomes one entry in the emitted `srcset`.
+    widths: Vec<u32>,
+    /// Intrinsic width of the source image. Used together with `height` to preserve
+    /// aspect ratio when deriving each variant's resize height.
+    width: u32,
+    /// Intrinsic height of the source image.
+    height: u32,
+    /// Image quality. 0-100.
+    #[prop(default = 75_u8)]
+    quality: u8,
+    /// Filter type for the conversion : Nearest, Triangle, CatmullRom, Gaussian, Lanczos3
+    #[prop(default = "catmullrom")]
+    filter: &'static str,
+    /// Resize type for the conversion : Fit, Cover, Thumbnail
+    #[prop(default = "fit")]
+    resize_type: &'static str,
+    /// Output format: Auto, Webp, Avif, Jpeg, Png.
+    #[prop(default = "auto")]
+    format: &'static str,
+    /// `sizes` attribute describing how much viewport width the image occupies at
+    /// each breakpoint, e.g. `"(max-width: 768px) 100vw, 50vw"`. Passed straight
+    /// through to the rendered `<img>`.
+    #[prop(into, default = "100vw".to_string())]
+    sizes: String,
     /// Will add blur image to head if true.
     #[prop(default = false)]
     blur: bool,
@@ -28,31 +57,142 @@ pub fn Picture(
     /// Style class for image
     #[prop(into, optional)]
     class: Option<AttributeValue>,
-    ) -> impl IntoView {
-
-    let screen = leptos::window();
-    let screen_width = screen.inner_width().unwrap_or_default().as_f64().unwrap_or_default() as usize;
-    let screen_height = screen.inner_height().unwrap_or_default().as_f64().unwrap_or_default() as usize;
-
-    let rules = ruleset(screen_width, screen_height);
-
-    let resize: String = rules.resize_type.to_string();
-    let filter: String = rules.filter.to_string();
-
-    view!{
-
-        <Image
-            src=src
-            alt=alt
-            class=class
-            priority=priority
-            blur=blur
-            lazy=lazy
-            width=rules.width
-            height=rules.height
-            quality=rules.quality
-            resize_type=&resize
-            filter=&filter
-        />
+) -> impl IntoView {
+    let variants: Vec<(u32, CachedImage)> = widths
+        .iter()
+        .map(|&target_width| {
+            let target_height = (height as u64 * target_width as u64 / width.max(1) as u64) as u32;
+            (
+                target_width,
+                CachedImage {
+                    src: src.clone(),
+                    option: CachedImageOption::Processed(ProcessedImage::new(
+                        vec![Box::new(ResizeProcessor {
+                            width: target_width,
+                            height: target_height,
+                            filter: filter.parse().unwrap_or_default(),
+                            resize_type: resize_type.parse().unwrap_or_default(),
+                        })],
+                        quality,
+                        format.parse().unwrap_or_default(),
+                    )),
+                },
+            )
+        })
+        .collect();
+
+    // `widths` isn't required to be sorted, so find the true max rather than assuming
+    // the last entry is the largest.
+    let largest = variants.iter().max_by_key(|(w, _)| *w).cloned();
+
+    let blur_image = CachedImage {
+        src: src.clone(),
+        option: CachedImageOption::BlurPlaceholder(Blur {
+            width: 20,
+            height: 20,
+            svg_width: 100,
+            svg_height: 100,
+            sigma: 15,
+        }),
+    };
+
+    let resource = crate::use_image_cache_resource();
+    let variants = store_value(variants);
+    let blur_image = store_value(blur_image);
+    let largest = store_value(largest);
+    let alt = store_value(alt);
+    let class = store_value(class);
+
+    view! {
+        <Suspense fallback=|| ()>
+            {move || {
+                resource
+                    .get()
+                    .map(|config| {
+                        let handler_path = config.api_handler_path;
+                        // Registers every responsive variant (and the blur placeholder) so
+                        // a later `generate_all` build pass can pre-render them for static
+                        // hosting. No-op on the client, where no `ImageOptimizer` is in context.
+                        if let Some(optimizer) = use_context::<crate::ImageOptimizer>() {
+                            variants.with_value(|variants| {
+                                for (_, image) in variants {
+                                    optimizer.register(image.clone());
+                                }
+                            });
+                            if blur {
+                                optimizer.register(blur_image.get_value());
+                            }
+                        }
+                        let srcset = variants
+                            .get_value()
+                            .iter()
+                            .map(|(w, image)| {
+                                format!("{} {}w", image.get_url_encoded(&handler_path), w)
+                            })
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let fallback_src = largest
+                            .get_value()
+                            .map(|(_, image)| image.get_url_encoded(&handler_path))
+                            .unwrap_or_default();
+
+                        let style = if blur {
+                            let placeholder_svg = config
+                                .cache
+                                .iter()
+                                .find(|(c, _)| blur_image.with_value(|b| b == c))
+                                .map(|(_, svg_data)| svg_data.clone());
+                            // Falls back to requesting the blur variant's URL directly, same
+                            // as `Image`'s `SvgImage::Request`, when it isn't in `config.cache`
+                            // yet (e.g. first render). That request is what populates the
+                            // cache for next time, via the handler's `add_file_to_cache`.
+                            let background_image = if let Some(svg_data) = placeholder_svg {
+                                use base64::{engine::general_purpose, Engine as _};
+                                let encoded = general_purpose::STANDARD.encode(svg_data.as_bytes());
+                                format!("url('data:image/svg+xml;base64,{encoded}')")
+                            } else {
+                                format!(
+                                    "url('{}')",
+                                    blur_image.get_value().get_url_encoded(&handler_path)
+                                )
+                            };
+                            Some(
+                                format!(
+                                    "color:transparent;background-size:cover;background-position:50% 50%;background-repeat:no-repeat;background-image:{background_image};",
+                                ),
+                            )
+                        } else {
+                            None
+                        };
+
+                        let loading = if lazy { "lazy" } else { "eager" };
+
+                        view! {
+                            {if priority {
+                                view! {
+                                    <Link rel="preload" as_="image" href=fallback_src.clone()/>
+                                }
+                                    .into_view()
+                            } else {
+                                ().into_view()
+                            }}
+                            <img
+                                alt=alt.get_value()
+                                class=class.get_value()
+                                decoding="async"
+                                loading=loading
+                                src=fallback_src
+                                srcset=srcset
+                                sizes=sizes.clone()
+                                style=style
+                                width=width
+                                height=height
+                            />
+                        }
+                            .into_view()
+                    })
+            }}
+
+        </Suspense>
     }
 }
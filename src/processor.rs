@@ -0,0 +1,450 @@
+use crate::optimizer::{FilterType, ResizeType};
+use image::{imageops::FilterType as ImageFilterType, DynamicImage, GenericImageView};
+
+/// A single step in a [`crate::ProcessedImage`] pipeline: a named, parseable image
+/// transform that contributes one segment to the cache key / URL and on-disk path.
+///
+/// Implement this to add a custom transform beyond the built-ins ([`ResizeProcessor`],
+/// [`CropProcessor`], [`RotateProcessor`], [`GrayscaleProcessor`], [`BlurProcessor`],
+/// [`WatermarkProcessor`]) and register it by extending [`parse_processor`].
+pub trait Processor: std::fmt::Debug + Send + Sync {
+    /// The query key this processor is encoded under, e.g. `"resize"`.
+    fn name(&self) -> &'static str;
+    /// This processor's query value, e.g. `"800x600xfitxcatmullrom"`. Together with
+    /// `name`, fully determines this processor's contribution to the cache key.
+    fn cache_key_segment(&self) -> String;
+    /// Applies this processor's transform to `img`.
+    fn process(&self, img: DynamicImage) -> DynamicImage;
+    fn clone_box(&self) -> Box<dyn Processor>;
+}
+
+impl Clone for Box<dyn Processor> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl PartialEq for Box<dyn Processor> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name() && self.cache_key_segment() == other.cache_key_segment()
+    }
+}
+
+impl Eq for Box<dyn Processor> {}
+
+impl std::hash::Hash for Box<dyn Processor> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.name().hash(state);
+        self.cache_key_segment().hash(state);
+    }
+}
+
+/// Attempts to parse a processor from a single `key=value` URL query pair, trying each
+/// built-in processor in turn. Returns `None` if `key` doesn't match any of them.
+pub(crate) fn parse_processor(key: &str, value: &str) -> Option<Box<dyn Processor>> {
+    ResizeProcessor::parse(key, value)
+        .or_else(|| CropProcessor::parse(key, value))
+        .or_else(|| RotateProcessor::parse(key, value))
+        .or_else(|| GrayscaleProcessor::parse(key, value))
+        .or_else(|| BlurProcessor::parse(key, value))
+        .or_else(|| WatermarkProcessor::parse(key, value))
+}
+
+/// The order processors are applied in, regardless of the order they were added in:
+/// crop and rotate establish framing first, resize scales the result, grayscale and
+/// blur are cheap final passes, and watermark goes last so it isn't itself resized,
+/// rotated, blurred, or desaturated.
+pub(crate) fn canonical_order(name: &str) -> u8 {
+    match name {
+        "crop" => 0,
+        "rotate" => 1,
+        "resize" => 2,
+        "grayscale" => 3,
+        "blur" => 4,
+        "watermark" => 5,
+        _ => u8::MAX,
+    }
+}
+
+/// Resizes the image to `width`x`height` using `resize_type`'s strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeProcessor {
+    pub width: u32,
+    pub height: u32,
+    pub filter: FilterType,
+    pub resize_type: ResizeType,
+}
+
+impl ResizeProcessor {
+    fn parse(key: &str, value: &str) -> Option<Box<dyn Processor>> {
+        if key != "resize" {
+            return None;
+        }
+        let mut parts = value.split('x');
+        let width = parts.next()?.parse().ok()?;
+        let height = parts.next()?.parse().ok()?;
+        let resize_type = parts.next()?.parse().ok()?;
+        let filter = parts.next()?.parse().ok()?;
+        Some(Box::new(ResizeProcessor {
+            width,
+            height,
+            filter,
+            resize_type,
+        }))
+    }
+}
+
+impl Processor for ResizeProcessor {
+    fn name(&self) -> &'static str {
+        "resize"
+    }
+
+    fn cache_key_segment(&self) -> String {
+        format!(
+            "{}x{}x{}x{}",
+            self.width, self.height, self.resize_type, self.filter
+        )
+    }
+
+    fn process(&self, img: DynamicImage) -> DynamicImage {
+        let filter: ImageFilterType = self.filter.into();
+        match self.resize_type {
+            ResizeType::Fit => img.resize(self.width, self.height, filter),
+            ResizeType::Cover => img.resize_to_fill(self.width, self.height, filter),
+            ResizeType::Thumbnail => img.thumbnail(self.width, self.height),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Processor> {
+        Box::new(*self)
+    }
+}
+
+/// Crops a `width`x`height` region out of the center of the image, with no scaling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CropProcessor {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CropProcessor {
+    fn parse(key: &str, value: &str) -> Option<Box<dyn Processor>> {
+        if key != "crop" {
+            return None;
+        }
+        let mut parts = value.split('x');
+        let width = parts.next()?.parse().ok()?;
+        let height = parts.next()?.parse().ok()?;
+        Some(Box::new(CropProcessor { width, height }))
+    }
+}
+
+impl Processor for CropProcessor {
+    fn name(&self) -> &'static str {
+        "crop"
+    }
+
+    fn cache_key_segment(&self) -> String {
+        format!("{}x{}", self.width, self.height)
+    }
+
+    fn process(&self, mut img: DynamicImage) -> DynamicImage {
+        let (source_width, source_height) = img.dimensions();
+        let crop_width = self.width.min(source_width);
+        let crop_height = self.height.min(source_height);
+        let x = (source_width - crop_width) / 2;
+        let y = (source_height - crop_height) / 2;
+        img.crop(x, y, crop_width, crop_height)
+    }
+
+    fn clone_box(&self) -> Box<dyn Processor> {
+        Box::new(*self)
+    }
+}
+
+/// Rotates the image by `degrees` (one of `0`, `90`, `180`, `270`), then optionally
+/// flips it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RotateProcessor {
+    pub degrees: u16,
+    pub flip: Option<Flip>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flip {
+    Horizontal,
+    Vertical,
+}
+
+impl RotateProcessor {
+    fn parse(key: &str, value: &str) -> Option<Box<dyn Processor>> {
+        if key != "rotate" {
+            return None;
+        }
+        let (degrees_str, flip) = if let Some(stripped) = value.strip_suffix('h') {
+            (stripped, Some(Flip::Horizontal))
+        } else if let Some(stripped) = value.strip_suffix('v') {
+            (stripped, Some(Flip::Vertical))
+        } else {
+            (value, None)
+        };
+        let degrees: u16 = degrees_str.parse().ok()?;
+        if !matches!(degrees, 0 | 90 | 180 | 270) {
+            return None;
+        }
+        Some(Box::new(RotateProcessor { degrees, flip }))
+    }
+}
+
+impl Processor for RotateProcessor {
+    fn name(&self) -> &'static str {
+        "rotate"
+    }
+
+    fn cache_key_segment(&self) -> String {
+        let suffix = match self.flip {
+            Some(Flip::Horizontal) => "h",
+            Some(Flip::Vertical) => "v",
+            None => "",
+        };
+        format!("{}{suffix}", self.degrees)
+    }
+
+    fn process(&self, img: DynamicImage) -> DynamicImage {
+        let rotated = match self.degrees % 360 {
+            90 => img.rotate90(),
+            180 => img.rotate180(),
+            270 => img.rotate270(),
+            _ => img,
+        };
+        match self.flip {
+            Some(Flip::Horizontal) => rotated.fliph(),
+            Some(Flip::Vertical) => rotated.flipv(),
+            None => rotated,
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Processor> {
+        Box::new(*self)
+    }
+}
+
+/// Converts the image to grayscale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrayscaleProcessor;
+
+impl GrayscaleProcessor {
+    fn parse(key: &str, _value: &str) -> Option<Box<dyn Processor>> {
+        if key != "grayscale" {
+            return None;
+        }
+        Some(Box::new(GrayscaleProcessor))
+    }
+}
+
+impl Processor for GrayscaleProcessor {
+    fn name(&self) -> &'static str {
+        "grayscale"
+    }
+
+    fn cache_key_segment(&self) -> String {
+        "1".to_string()
+    }
+
+    fn process(&self, img: DynamicImage) -> DynamicImage {
+        img.grayscale()
+    }
+
+    fn clone_box(&self) -> Box<dyn Processor> {
+        Box::new(*self)
+    }
+}
+
+/// Applies a gaussian blur with standard deviation `sigma` to the whole image.
+///
+/// Distinct from [`crate::Blur`], which generates a tiny blurred SVG placeholder
+/// rather than transforming the full-size output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlurProcessor {
+    pub sigma: f32,
+}
+
+impl Eq for BlurProcessor {}
+
+impl BlurProcessor {
+    fn parse(key: &str, value: &str) -> Option<Box<dyn Processor>> {
+        if key != "blur" {
+            return None;
+        }
+        let sigma = value.parse().ok()?;
+        Some(Box::new(BlurProcessor { sigma }))
+    }
+}
+
+impl Processor for BlurProcessor {
+    fn name(&self) -> &'static str {
+        "blur"
+    }
+
+    fn cache_key_segment(&self) -> String {
+        self.sigma.to_string()
+    }
+
+    fn process(&self, img: DynamicImage) -> DynamicImage {
+        img.blur(self.sigma)
+    }
+
+    fn clone_box(&self) -> Box<dyn Processor> {
+        Box::new(*self)
+    }
+}
+
+/// Corner a [`WatermarkProcessor`]'s overlay is anchored to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl std::fmt::Display for WatermarkPosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            Self::TopLeft => "topleft",
+            Self::TopRight => "topright",
+            Self::BottomLeft => "bottomleft",
+            Self::BottomRight => "bottomright",
+        };
+        write!(f, "{s}")
+    }
+}
+
+impl std::str::FromStr for WatermarkPosition {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "topleft" => Ok(Self::TopLeft),
+            "topright" => Ok(Self::TopRight),
+            "bottomleft" => Ok(Self::BottomLeft),
+            "bottomright" => Ok(Self::BottomRight),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Stamps a solid, semi-transparent badge of `width`x`height` into one corner of the
+/// image, at `opacity` (0-255).
+///
+/// [`Processor::process`] only has the decoded pixels to work with, not `site_root` or
+/// any other file-system access, so this can't composite an arbitrary logo image the
+/// way a full watermarking pipeline would; it covers the "stamp a marker on the output"
+/// use case built entirely out of the pipeline's existing inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatermarkProcessor {
+    pub position: WatermarkPosition,
+    pub width: u32,
+    pub height: u32,
+    pub opacity: u8,
+}
+
+impl WatermarkProcessor {
+    fn parse(key: &str, value: &str) -> Option<Box<dyn Processor>> {
+        if key != "watermark" {
+            return None;
+        }
+        let mut parts = value.split('x');
+        let position = parts.next()?.parse().ok()?;
+        let width = parts.next()?.parse().ok()?;
+        let height = parts.next()?.parse().ok()?;
+        let opacity = parts.next()?.parse().ok()?;
+        Some(Box::new(WatermarkProcessor {
+            position,
+            width,
+            height,
+            opacity,
+        }))
+    }
+}
+
+impl Processor for WatermarkProcessor {
+    fn name(&self) -> &'static str {
+        "watermark"
+    }
+
+    fn cache_key_segment(&self) -> String {
+        format!(
+            "{}x{}x{}x{}",
+            self.position, self.width, self.height, self.opacity
+        )
+    }
+
+    fn process(&self, img: DynamicImage) -> DynamicImage {
+        let (img_width, img_height) = img.dimensions();
+        let width = self.width.min(img_width);
+        let height = self.height.min(img_height);
+        let (x, y) = match self.position {
+            WatermarkPosition::TopLeft => (0, 0),
+            WatermarkPosition::TopRight => (img_width - width, 0),
+            WatermarkPosition::BottomLeft => (0, img_height - height),
+            WatermarkPosition::BottomRight => (img_width - width, img_height - height),
+        };
+
+        let badge =
+            image::RgbaImage::from_pixel(width, height, image::Rgba([0, 0, 0, self.opacity]));
+        let mut out = img.to_rgba8();
+        image::imageops::overlay(&mut out, &badge, x as i64, y as i64);
+        DynamicImage::ImageRgba8(out)
+    }
+
+    fn clone_box(&self) -> Box<dyn Processor> {
+        Box::new(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_processor_round_trips_cache_key_segments() {
+        let cases: &[(&str, &str)] = &[
+            ("resize", "800x600xfitxcatmullrom"),
+            ("crop", "400x300"),
+            ("rotate", "90h"),
+            ("grayscale", "1"),
+            ("blur", "2.5"),
+            ("watermark", "bottomrightx200x80x128"),
+        ];
+
+        for (name, segment) in cases {
+            let processor = parse_processor(name, segment)
+                .unwrap_or_else(|| panic!("failed to parse {name}={segment}"));
+            assert_eq!(processor.name(), *name);
+            assert_eq!(&processor.cache_key_segment(), segment);
+        }
+    }
+
+    #[test]
+    fn parse_processor_rejects_unknown_keys() {
+        assert!(parse_processor("unknown", "anything").is_none());
+    }
+
+    #[test]
+    fn rotate_rejects_degrees_outside_the_supported_set() {
+        assert!(parse_processor("rotate", "45").is_none());
+        assert!(parse_processor("rotate", "45h").is_none());
+        assert!(parse_processor("rotate", "90").is_some());
+    }
+
+    #[test]
+    fn canonical_order_places_watermark_last() {
+        let mut names = vec!["watermark", "blur", "grayscale", "resize", "rotate", "crop"];
+        names.sort_by_key(|name| canonical_order(name));
+        assert_eq!(
+            names,
+            vec!["crop", "rotate", "resize", "grayscale", "blur", "watermark"]
+        );
+    }
+}
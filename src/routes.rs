@@ -3,7 +3,7 @@ use axum::extract::FromRef;
 use axum::response::Response as AxumResponse;
 use axum::{
     body::Body,
-    http::{Request, Response, Uri},
+    http::{header, Request, Response, Uri},
     response::IntoResponse,
 };
 use leptos::LeptosOptions;
@@ -87,12 +87,39 @@ async fn image_cache_handler_inner(
     req: Request<Body>,
 ) -> AxumResponse {
     let root = options.site_root.clone();
-    let cache_result = check_cache_image(&optimizer, req.uri().clone()).await;
+    let accept = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let if_none_match = req
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let cache_result = check_cache_image(&optimizer, req.uri().clone(), accept.as_deref()).await;
 
     match cache_result {
-        Ok(Some(uri)) => {
+        Ok(Some((uri, etag))) => {
+            if if_none_match.as_deref() == Some(etag.as_str()) {
+                return Response::builder()
+                    .status(304)
+                    .body(Body::empty())
+                    .unwrap()
+                    .into_response();
+            }
+
             let response = execute_file_handler(uri, &root).await.unwrap();
-            response.into_response()
+            let mut response = response.into_response();
+            let headers = response.headers_mut();
+            headers.insert(
+                header::CACHE_CONTROL,
+                header::HeaderValue::from_static("public, max-age=31536000, immutable"),
+            );
+            if let Ok(value) = header::HeaderValue::from_str(&etag) {
+                headers.insert(header::ETAG, value);
+            }
+            response
         }
 
         Ok(None) => Response::builder()
@@ -126,11 +153,16 @@ async fn execute_file_handler(
 async fn check_cache_image(
     optimizer: &ImageOptimizer,
     uri: Uri,
-) -> Result<Option<Uri>, CreateImageError> {
+    accept: Option<&str>,
+) -> Result<Option<(Uri, String)>, CreateImageError> {
     let url = uri.to_string();
 
     let cache_image = {
         if let Some(img) = CachedImage::from_url_encoded(&url).ok() {
+            // Fold the `Accept`-negotiated format into the image before it's used to
+            // derive the cache key / file path, so each format is cached separately.
+            let img = resolve_output_format(img, accept);
+
             let result = optimizer.create_image(&img).await;
 
             if let Ok(true) = result {
@@ -145,6 +177,9 @@ async fn check_cache_image(
         }
     };
 
+    // The path is content-addressed from `cache_image`, so its hash also makes a
+    // correct, stable ETag: identical params always resolve to the same value.
+    let etag = format!("\"{}\"", cache_image.content_hash());
     let file_path = cache_image.get_file_path();
 
     add_file_to_cache(optimizer, cache_image).await;
@@ -153,17 +188,27 @@ async fn check_cache_image(
     let maybe_uri = (uri_string).parse::<Uri>().ok();
 
     if let Some(uri) = maybe_uri {
-        Ok(Some(uri))
+        Ok(Some((uri, etag)))
     } else {
         tracing::error!("Failed to create uri: File path {file_path}");
         Ok(None)
     }
 }
 
+/// Resolves `OutputFormat::Auto` on a processed image's output format against the
+/// request's `Accept` header, selecting the best format the browser advertises support
+/// for.
+fn resolve_output_format(mut image: CachedImage, accept: Option<&str>) -> CachedImage {
+    if let CachedImageOption::Processed(processed) = &mut image.option {
+        processed.format = processed.format.resolve(accept);
+    }
+    image
+}
+
 // When the image is created, it will be added to the cache.
 // Mostly helpful for dev server startup.
 async fn add_file_to_cache(optimizer: &ImageOptimizer, image: CachedImage) {
-    if let CachedImageOption::Blur(_) = image.option {
+    if let CachedImageOption::BlurPlaceholder(_) = image.option {
         add_image_cache(optimizer, vec![image]).await;
     }
 }
@@ -174,7 +219,12 @@ where
 {
     let images = images
         .into_iter()
-        .filter(|image| matches!(image.option, crate::optimizer::CachedImageOption::Blur(_)))
+        .filter(|image| {
+            matches!(
+                image.option,
+                crate::optimizer::CachedImageOption::BlurPlaceholder(_)
+            )
+        })
         .filter(|image| optimizer.cache.get(&image).is_none());
 
     for image in images {